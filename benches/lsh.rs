@@ -11,8 +11,9 @@ fn bench_randomproj_16_f32_1024(c: &mut Criterion) {
 
     const CM_TREE: lsh::ConstructionMethod = lsh::ConstructionMethod::Tree;
     const CM_CONCAT: lsh::ConstructionMethod = lsh::ConstructionMethod::Concatenate;
+    const W: usize = lsh::width_for(N);
 
-    let rp = lsh::RandomProjection::<'_, f32, D, N, CM_TREE>::new(&arr);
+    let rp = lsh::RandomProjection::<'_, f32, D, N, W, CM_TREE>::new(&arr);
     c.bench_function("bench_randomproj_f32_tree", |b| {
         let qv = [0.0f32; D];
         b.iter(|| {
@@ -20,7 +21,7 @@ fn bench_randomproj_16_f32_1024(c: &mut Criterion) {
         })
     });
 
-    let rp = lsh::RandomProjection::<'_, f32, D, N, CM_CONCAT>::new(&arr);
+    let rp = lsh::RandomProjection::<'_, f32, D, N, W, CM_CONCAT>::new(&arr);
     c.bench_function("bench_randomproj_f32_concatenate", |b| {
         let qv = [0.0f32; D];
         b.iter(|| {
@@ -18,6 +18,25 @@ where
     arr
 }
 
+/// Like `build_random_unit_hyperplanes`, but into a flat, heap-allocated `Matrix` with
+/// row stride `D` instead of a stack-resident `[[T; D]; N]`, for large hyperplane banks.
+#[cfg(feature = "alloc")]
+pub fn build_random_unit_hyperplanes_matrix<T, const D: usize, R>(
+    n: usize,
+    rng: &mut R,
+) -> crate::matrix::Matrix<T>
+where
+    T: RandomUnitVector<D, Output = [T; D]> + Default + Copy,
+    R: Rng,
+{
+    let mut m = crate::matrix::Matrix::new(alloc::vec![T::default(); n * D], D);
+    for i in 0..n {
+        m[i].copy_from_slice(&T::sample(rng));
+    }
+
+    m
+}
+
 pub trait RandomUnitVector<const D: usize> {
     type Output;
     fn sample<R: Rng>(rng: &mut R) -> Self::Output;
@@ -1,61 +1,314 @@
+use rand::Rng;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::marker::PhantomData;
+use std::vec;
+use std::vec::Vec;
+
 const SKIP_LIST_PROB: f32 = 0.15f32;
 
 pub trait Metric<I, O: Ord> {
     fn d(a: &I, b: &I) -> O;
 }
 
-mod hnsw_ptr {
-    use super::Metric;
-    use rand::Rng;
+type Vertex = usize;
+/// A directed edge to another vertex.
+type Edge = Vertex;
+
+/// The neighborhood of a vertex, holding up to `M` edges.
+#[derive(Debug)]
+struct Neighborhood<const M: usize> {
+    neighbors: [Edge; M],
+    len: usize,
+}
+
+impl<const M: usize> Neighborhood<M> {
+    fn empty() -> Self {
+        Self {
+            neighbors: [0; M],
+            len: 0,
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = Edge> + '_ {
+        self.neighbors[..self.len].iter().copied()
+    }
+
+    /// Replace the neighborhood's contents with `edges`, keeping at most `M` of them.
+    fn set(&mut self, edges: &[Edge]) {
+        self.len = edges.len().min(M);
+        self.neighbors[..self.len].copy_from_slice(&edges[..self.len]);
+    }
+}
+
+impl<const M: usize> IntoIterator for Neighborhood<M> {
+    type Item = Edge;
+    type IntoIter = std::iter::Take<std::array::IntoIter<Self::Item, M>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.neighbors.into_iter().take(self.len)
+    }
+}
+
+/// A single level of the index. Vertices are addressed by the same global id across
+/// every layer they participate in; a layer only connects the subset of ids that were
+/// assigned a level at or above it.
+#[derive(Debug)]
+struct Layer<const N: usize, const M: usize, I, O> {
+    vertices: [I; N],
+    neighbors: [Neighborhood<M>; N],
+
+    _metric: PhantomData<O>,
+}
+
+impl<const N: usize, const M: usize, I: Default, O> Layer<N, M, I, O> {
+    fn empty() -> Self {
+        Self {
+            vertices: std::array::from_fn(|_| I::default()),
+            neighbors: std::array::from_fn(|_| Neighborhood::empty()),
+            _metric: PhantomData,
+        }
+    }
+}
+
+impl<const N: usize, const M: usize, I, O: Ord + Copy> Layer<N, M, I, O> {
+    /// Greedily walk from `entry` toward whichever neighbor minimizes `Met::d`,
+    /// stopping once no neighbor is closer than the current vertex.
+    fn greedy_search<Met: Metric<I, O>>(&self, entry: Vertex, q: &I) -> Vertex {
+        let mut best = entry;
+        let mut best_d = Met::d(&self.vertices[best], q);
+
+        loop {
+            let mut improved = false;
+            for n in self.neighbors[best].iter() {
+                let d = Met::d(&self.vertices[n], q);
+                if d < best_d {
+                    best = n;
+                    best_d = d;
+                    improved = true;
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+
+        best
+    }
+
+    /// Bounded beam search over this layer, returning up to `ef` vertices nearest `q`,
+    /// sorted nearest-first.
+    ///
+    /// Follows the classic Dijkstra-with-`BinaryHeap` pattern: `candidates` is a
+    /// min-heap of unvisited vertices ordered by distance to `q`, `results` is a
+    /// max-heap capped at `ef` holding the current best results. The candidate heap
+    /// is drained until the closest remaining candidate is farther than the worst
+    /// kept result and `results` is already full.
+    fn search_layer<Met: Metric<I, O>>(&self, entry: Vertex, q: &I, ef: usize) -> Vec<(Vertex, O)> {
+        let mut visited = vec![false; N];
+        visited[entry] = true;
+
+        let entry_d = Met::d(&self.vertices[entry], q);
+        let mut candidates = BinaryHeap::new();
+        candidates.push(Reverse((entry_d, entry)));
+
+        let mut results = BinaryHeap::new();
+        results.push((entry_d, entry));
+
+        while let Some(Reverse((c_d, c))) = candidates.pop() {
+            if results.len() >= ef {
+                if let Some(&(worst, _)) = results.peek() {
+                    if c_d > worst {
+                        break;
+                    }
+                }
+            }
+
+            for n in self.neighbors[c].iter() {
+                if visited[n] {
+                    continue;
+                }
+                visited[n] = true;
+
+                let n_d = Met::d(&self.vertices[n], q);
+                let is_closer = results.peek().is_none_or(|&(worst, _)| n_d < worst);
+
+                if results.len() < ef || is_closer {
+                    candidates.push(Reverse((n_d, n)));
+                    results.push((n_d, n));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        let mut out: Vec<(Vertex, O)> = results.into_iter().map(|(d, v)| (v, d)).collect();
+        out.sort_by_key(|&(_, d)| d);
+        out
+    }
+
+    /// Connect `v` bidirectionally to each of `candidates`, pruning any neighbor whose
+    /// degree now exceeds `M` down to its `M` closest edges.
+    fn connect<Met: Metric<I, O>>(&mut self, v: Vertex, candidates: &[(Vertex, O)]) {
+        let edges: Vec<Edge> = candidates.iter().map(|&(c, _)| c).collect();
+        self.neighbors[v].set(&edges);
+
+        for &(c, _) in candidates {
+            let mut back_edges: Vec<Edge> = self.neighbors[c].iter().collect();
+            back_edges.push(v);
 
-    type Vertex = usize;
-    /// A directed edge to another vertex.
-    type Edge = Vertex;
+            if back_edges.len() > M {
+                let origin = &self.vertices[c];
+                back_edges.sort_by_key(|&n| Met::d(&self.vertices[n], origin));
+                back_edges.truncate(M);
+            }
 
-    /// The neighborhood of a vertex containing M edges.
-    #[derive(Debug)]
-    struct Neighborhood<const M: usize> {
-        neighbors: [Edge; M],
+            self.neighbors[c].set(&back_edges);
+        }
     }
+}
 
-    impl<const M: usize> IntoIterator for Neighborhood<M> {
-        type Item = Edge;
-        type IntoIter = std::array::IntoIter<Self::Item, M>;
+/// A Hierarchical Navigable Small World index over `N` elements of type `I`, with
+/// at most `L` layers and up to `M` bidirectional edges per vertex per layer.
+#[derive(Debug)]
+pub struct HNSWDB<const L: usize, const N: usize, const M: usize, I, O> {
+    layers: [Layer<N, M, I, O>; L],
+    /// Per-vertex top level, indexed by global vertex id.
+    max_level: [usize; N],
+    len: usize,
+    entry: Option<Vertex>,
+    top: usize,
+}
 
-        fn into_iter(self) -> Self::IntoIter {
-            self.neighbors.into_iter()
+impl<const L: usize, const N: usize, const M: usize, I: Default, O> HNSWDB<L, N, M, I, O> {
+    pub fn new() -> Self {
+        Self {
+            layers: std::array::from_fn(|_| Layer::empty()),
+            max_level: [0; N],
+            len: 0,
+            entry: None,
+            top: 0,
         }
     }
+}
+
+impl<const L: usize, const N: usize, const M: usize, I: Default, O> Default
+    for HNSWDB<L, N, M, I, O>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const L: usize, const N: usize, const M: usize, I: Clone, O: Ord + Copy>
+    HNSWDB<L, N, M, I, O>
+{
+    /// Assign a level to a newly inserted element by repeatedly "coin-flipping" with
+    /// probability `SKIP_LIST_PROB`: the level is the number of consecutive successes,
+    /// capped so it always fits within the configured `L` layers.
+    fn random_level<R: Rng>(rng: &mut R) -> usize {
+        let mut level = 0;
+        while level < L - 1 && rng.gen::<f32>() < SKIP_LIST_PROB {
+            level += 1;
+        }
+        level
+    }
+
+    /// Insert `v`, using `ef_construction` as the beam width while collecting
+    /// candidate neighbors at each layer.
+    pub fn insert<Met: Metric<I, O>, R: Rng>(&mut self, rng: &mut R, ef_construction: usize, v: I) {
+        assert!(self.len < N, "HNSWDB is at capacity");
+
+        let level = Self::random_level(rng);
+        let id = self.len;
+        self.len += 1;
+        self.max_level[id] = level;
 
-    #[derive(Debug)]
-    struct Layer<const N: usize, const M: usize, I, O> {
-        vertices: [I; N],
-        neighbors: [Neighborhood<M>; N],
+        for layer in self.layers[..=level].iter_mut() {
+            layer.vertices[id] = v.clone();
+        }
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(id);
+            self.top = level;
+            return;
+        };
+
+        // Greedily descend from the current entry point down to `level + 1`,
+        // tracking only the single nearest vertex at each layer.
+        let mut cur = entry;
+        for l in (level + 1..=self.top).rev() {
+            cur = self.layers[l].greedy_search::<Met>(cur, &v);
+        }
+
+        // From `level` (or the current top layer, if lower) down to 0, beam search for
+        // candidates and connect `id` to its `M` nearest neighbors at each layer.
+        for l in (0..=level.min(self.top)).rev() {
+            let mut candidates = self.layers[l].search_layer::<Met>(cur, &v, ef_construction);
+            candidates.truncate(M);
 
-        _metric: std::marker::PhantomData<O>,
+            if let Some(&(next, _)) = candidates.first() {
+                cur = next;
+            }
+
+            self.layers[l].connect::<Met>(id, &candidates);
+        }
+
+        if level > self.top {
+            self.top = level;
+            self.entry = Some(id);
+        }
     }
 
-    impl<const N: usize, const M: usize, I, O: Ord> Layer<N, M, I, O> {
-        fn entry<R: Rng>(rng: &mut R) -> usize {
-            return rng.gen::<usize>() % N;
+    /// Return the `k` vertices nearest `q`, using `ef` as the layer-0 beam width.
+    pub fn query<Met: Metric<I, O>>(&self, q: &I, k: usize, ef: usize) -> Vec<Vertex> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+
+        let mut cur = entry;
+        for l in (1..=self.top).rev() {
+            cur = self.layers[l].greedy_search::<Met>(cur, q);
         }
 
-        pub fn search<R: Rng>(&self, rng: &mut R, m: impl Metric<I, O>) -> usize {
-            let idx = Layer::<N, M, I, O>::entry(rng);
-            // TODO: If for some reason this evaluates to `None`, would we rather panic (notifying the programmer
-            // that `entry` bugged or should we fallback on a pre-defined entry.
-            let search_neighborhood = self.neighbors.get(idx).unwrap();
+        let mut candidates = self.layers[0].search_layer::<Met>(cur, q, ef.max(k));
+        candidates.truncate(k);
+        candidates.into_iter().map(|(v, _)| v).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            // Find the minimum distance.
-            // let idx = search_neighborhood.into_iter().min_by()
-            todo!()
+    #[derive(Default, Clone, Copy, Debug)]
+    struct Point([i64; 2]);
+
+    struct SquaredEuclidean;
+
+    impl Metric<Point, i64> for SquaredEuclidean {
+        fn d(a: &Point, b: &Point) -> i64 {
+            a.0.iter()
+                .zip(b.0.iter())
+                .map(|(x, y)| (x - y) * (x - y))
+                .sum()
         }
     }
 
-    struct HNSWDB<const L: usize, const M: usize> {}
+    #[test]
+    fn test_self_query() {
+        const N: usize = 64;
+        let mut rng = rand::thread_rng();
+        let mut db = HNSWDB::<4, N, 8, Point, i64>::new();
+
+        let points: Vec<Point> = (0..N as i64).map(|i| Point([i, -i])).collect();
+        for &p in &points {
+            db.insert::<SquaredEuclidean, _>(&mut rng, 32, p);
+        }
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
+        let target = points[10];
+        let result = db.query::<SquaredEuclidean>(&target, 1, 32);
+        assert_eq!(result.first().map(|&id| points[id].0), Some(target.0));
     }
 }
@@ -1,21 +1,23 @@
-use super::lsh::LSH;
+use super::probe;
+use super::lsh::{self, BitCode, ConstructionMethod, HyperplaneBank, RandomProjection, LSH};
 
 type TupleRange = (usize, usize);
 
-trait Index<I> {
+trait Index<I, const W: usize> {
     /// Given a bin, return the set of identifiers that belong to that bin.
     /// TODO: Utilize an iterator.
-    fn get(&self, bin: usize) -> Option<&[I]>;
+    fn get(&self, bin: BitCode<W>) -> Option<&[I]>;
 }
 
 /// An index with the range search mechanism facilitated with an array.
-struct ArrIndex<const N: usize, I, const NB: usize> {
+struct ArrIndex<const N: usize, I, const NB: usize, const W: usize> {
     ranges: [Option<TupleRange>; NB],
     arr: [I; N],
 }
 
-impl<const N: usize, I, const NB: usize> Index<I> for ArrIndex<N, I, NB> {
-    fn get(&self, bin: usize) -> Option<&[I]> {
+impl<const N: usize, I, const NB: usize, const W: usize> Index<I, W> for ArrIndex<N, I, NB, W> {
+    fn get(&self, bin: BitCode<W>) -> Option<&[I]> {
+        let bin = bin.to_usize_truncating();
         if let Some(range) = self.ranges.get(bin).cloned().flatten() {
             Some(&self.arr[range.0..range.1])
         } else {
@@ -24,37 +26,156 @@ impl<const N: usize, I, const NB: usize> Index<I> for ArrIndex<N, I, NB> {
     }
 }
 
-impl<const N: usize, const NB: usize> ArrIndex<N, usize, NB> {
-    fn build_concatenate<'c, T, const D: usize, L: LSH<'c, T, D>>(
-        x: &'c [[T; D]; N],
-        l: &L,
-    ) -> Self {
+impl<const N: usize, const NB: usize, const W: usize> ArrIndex<N, usize, NB, W> {
+    /// `x` is anything that can hand back `N` `D`-dimensional rows by index — a borrowed
+    /// fixed array or a borrowed `Matrix` — so large vector sets can be staged on the
+    /// heap instead of as a stack-resident `[[T; D]; N]`.
+    fn build_concatenate<'c, T, const D: usize, X, L>(x: X, l: &L) -> Self
+    where
+        T: 'c,
+        X: HyperplaneBank<'c, T, D>,
+        L: LSH<'c, T, D, W>,
+    {
         let mut ranges: [Option<TupleRange>; NB] = [None; NB];
         let mut arr = [0usize; N];
 
         // Build `(idx, proj)` then sort by `proj`. Compute the range for each `proj` value.
         // Drop `proj` within `tmp_idx_proj` to build `arr`.
-        let mut tmp_idx_proj = [(0usize, 0usize); N];
-        for (idx, (proj_mem, query)) in tmp_idx_proj.iter_mut().zip(x).enumerate() {
-            *proj_mem = (idx, l.bin(query));
+        let mut tmp_idx_proj = [(0usize, BitCode::<W>::default()); N];
+        for (idx, proj_mem) in tmp_idx_proj.iter_mut().enumerate() {
+            *proj_mem = (idx, l.bin(x.row(idx)));
         }
 
         // TODO: `sort_unstable_by_key` throws some lifetime issues.
-        tmp_idx_proj
-            .sort_unstable_by(|(_, a_proj), (_, b_proj)| a_proj.partial_cmp(b_proj).unwrap());
+        tmp_idx_proj.sort_unstable_by(|(_, a_proj), (_, b_proj)| a_proj.cmp(b_proj));
 
+        // A single forward pass over the now-sorted bin boundaries: whenever the bin
+        // changes, the just-finished bin's range closes at the current index, and the
+        // new bin's range opens there.
+        let mut open: Option<(usize, usize)> = None; // (bin, start)
         for (idx, (arr_mem, (id, proj))) in arr.iter_mut().zip(tmp_idx_proj).enumerate() {
             *arr_mem = id; // This value of `arr` becomes the current idx.
 
-            // Update the range.
-            let potential_range = ranges.get_mut(proj).unwrap();
-            match potential_range {
-                // TODO: This update writes a new value when `idx + 1` is technically all that's needed.
-                Some(existing_range) => *potential_range = Some((existing_range.0, idx)), // Update the range.
-                None => *potential_range = Some((idx, N)), // If this range is unset, the range exists from `idx` to `N`.
+            let bin = proj.to_usize_truncating();
+            match open {
+                Some((open_bin, _)) if open_bin == bin => {}
+                Some((open_bin, start)) => {
+                    ranges[open_bin] = Some((start, idx));
+                    open = Some((bin, idx));
+                }
+                None => open = Some((bin, idx)),
             }
         }
+        if let Some((open_bin, start)) = open {
+            ranges[open_bin] = Some((start, N));
+        }
 
         ArrIndex { ranges, arr }
     }
 }
+
+/// A locality-sensitive-hashing index: `NB` random hyperplanes hash each of the `N`
+/// stored `D`-dimensional vectors into a bucket under `CM`, and `ann` returns the
+/// identifiers in a query's bucket and, on request, in further probed buckets.
+pub struct LSHDB<
+    const NB: usize,
+    const N: usize,
+    T,
+    const D: usize,
+    I,
+    const CM: ConstructionMethod = { ConstructionMethod::Concatenate },
+> where
+    [(); lsh::width_for(NB)]: Sized,
+    [(); 1 << NB]: Sized,
+{
+    hp: crate::matrix::Matrix<T>,
+    ids: [I; N],
+    index: ArrIndex<N, usize, { 1 << NB }, { lsh::width_for(NB) }>,
+}
+
+impl<const NB: usize, const N: usize, T, const D: usize, I, const CM: ConstructionMethod>
+    LSHDB<NB, N, T, D, I, CM>
+where
+    T: for<'x> lsh::hyperplane::ArcCos<'x, T, D>
+        + super::distribution::RandomUnitVector<D, Output = [T; D]>
+        + Default
+        + Copy
+        + PartialOrd
+        + core::ops::Neg<Output = T>
+        + Into<lsh::hyperplane::Sign>
+        + Into<f64>,
+    I: Copy,
+    [(); lsh::width_for(NB)]: Sized,
+    [(); 1 << NB]: Sized,
+    [(); NB.ilog2() as usize]: Sized,
+    lsh::ConstAssert<{ lsh::fits_in_bitcode(CM, NB, lsh::width_for(NB)) }>:,
+{
+    /// Build an index over `vectors`. `_tables` is reserved for selecting the number of
+    /// independently-hashed tables to probe; a single table is built for now.
+    pub fn new<R: rand::Rng>(
+        rng: &mut R,
+        vectors: &[(I, [T; D]); N],
+        _tables: Option<usize>,
+    ) -> Self {
+        // Stage both the hyperplane normals and the vectors in flat, heap-allocated
+        // buffers rather than stack-resident `[[T; D]; NB]` / `[[T; D]; N]`, which get
+        // expensive to carry around for large `NB`, `N`, or `D`.
+        let hp = super::distribution::build_random_unit_hyperplanes_matrix::<T, D, R>(NB, rng);
+
+        let mut raw = crate::matrix::Matrix::new(alloc::vec![T::default(); N * D], D);
+        for (i, (_, v)) in vectors.iter().enumerate() {
+            raw[i].copy_from_slice(v);
+        }
+        let ids: [I; N] = core::array::from_fn(|i| vectors[i].0);
+
+        let rp = RandomProjection::<
+            '_,
+            T,
+            D,
+            NB,
+            { lsh::width_for(NB) },
+            CM,
+            &'_ crate::matrix::Matrix<T>,
+        >::new(&hp);
+        let index = ArrIndex::build_concatenate(&raw, &rp);
+
+        Self { hp, ids, index }
+    }
+
+    /// Return the identifiers found within the `probes` most likely buckets for `q`, in
+    /// order of decreasing likelihood: `q`'s own bucket first, then `probes - 1` more
+    /// visited via multi-probe LSH, cheaper than hashing `q` into more independent
+    /// tables for the same recall gain. `probes == 1` reproduces the single-bucket
+    /// lookup this method used to be. The extra probes flip concatenated per-hyperplane
+    /// bits, so under `ConstructionMethod::Tree` only `q`'s own bucket is searched.
+    pub fn ann<'q>(&'q self, q: &'q [T; D], probes: usize) -> alloc::vec::Vec<&'q I> {
+        let rp = RandomProjection::<
+            '_,
+            T,
+            D,
+            NB,
+            { lsh::width_for(NB) },
+            CM,
+            &'_ crate::matrix::Matrix<T>,
+        >::new(&self.hp);
+        // `bin` hashes under `CM`, matching how the index was built.
+        let bin = rp.bin(q);
+
+        let mut out = alloc::vec::Vec::new();
+        for &pos in self.index.get(bin).into_iter().flatten() {
+            out.push(&self.ids[pos]);
+        }
+
+        if probes > 1 && CM == ConstructionMethod::Concatenate {
+            let (_, scores) = rp.bin_with_scores(q);
+            let scores: alloc::vec::Vec<f64> = scores.iter().map(|&s| s.into()).collect();
+            for probe in probe::probe_bins(bin, &scores, probes - 1) {
+                for &pos in self.index.get(probe).into_iter().flatten() {
+                    out.push(&self.ids[pos]);
+                }
+            }
+        }
+
+        out
+    }
+}
@@ -0,0 +1,28 @@
+//! ns_ann: approximate nearest neighbor search (LSH and HNSW).
+//!
+//! The LSH core (`lsh`, `distribution`, `hyperplane`) is `no_std` and allocator-free:
+//! `RandomProjection::bin` works entirely on the stack, for resource-constrained
+//! targets that can bucket vectors via SimHash but have no allocator. `index` builds on
+//! `alloc` for its heap-backed `Matrix` buffers and `Vec` query results, and `hnsw` needs the full standard
+//! library for its heap-based beam search; both are gated behind their own features.
+#![no_std]
+#![feature(generic_const_exprs)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod distribution;
+pub mod lsh;
+pub mod probe;
+
+#[cfg(feature = "alloc")]
+pub mod index;
+
+#[cfg(feature = "alloc")]
+pub mod matrix;
+
+#[cfg(feature = "std")]
+pub mod hnsw;
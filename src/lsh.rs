@@ -1,50 +1,123 @@
 // src/lsh.rs; Copyright 2023, Ethan Jones. See LICENSE for licensing information.
 
-/// Anything that maps [T; D] to a length `log2(usize)` Hamming space.
-pub trait LSH<'a, T, const D: usize> {
-    fn bin(&self, q: &'a [T; D]) -> usize;
+/// A platform-independent, arbitrarily wide bit-code: `W` 64-bit words, compared
+/// lexicographically word by word. Replaces packing hash bits into a `usize`, which caps
+/// the number of usable hyperplanes at `usize::BITS` and makes behavior depend on the
+/// host word size.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BitCode<const W: usize> {
+    words: [u64; W],
 }
 
-// Allow the type system to bind `N` to be lte `usize::BITS`; this is dependent on
+impl<const W: usize> Default for BitCode<W> {
+    fn default() -> Self {
+        Self { words: [0u64; W] }
+    }
+}
+
+impl<const W: usize> BitCode<W> {
+    /// Set bit `idx`, where bit 0 is the least-significant bit of `words[0]`.
+    fn set(&mut self, idx: usize) {
+        self.words[idx / 64] |= 1u64 << (idx % 64);
+    }
+
+    /// Return a copy of `self` with bit `idx` flipped.
+    pub fn flip(mut self, idx: usize) -> Self {
+        self.words[idx / 64] ^= 1u64 << (idx % 64);
+        self
+    }
+
+    /// Pack the low 64 bits into a `usize`, truncating any higher words. Intended for
+    /// indexing dense arrays whose addressable range is known to fit within a word, such
+    /// as `ArrIndex`'s bin ranges.
+    pub(super) fn to_usize_truncating(self) -> usize {
+        self.words[0] as usize
+    }
+}
+
+/// Anything that maps `[T; D]` to a `BitCode<W>`.
+pub trait LSH<'a, T, const D: usize, const W: usize> {
+    fn bin(&self, q: &'a [T; D]) -> BitCode<W>;
+}
+
+// Allow the type system to bind `N` to be lte `W * 64`; this is dependent on
 // the `ConstructionMethod` being utilized.
 // See: https://github.com/rust-lang/rust/issues/68436#issuecomment-709786363
 pub struct ConstAssert<const ASSERT: ()>;
-pub const fn fits_in_usize(cm: ConstructionMethod, n: usize) {
+pub const fn fits_in_bitcode(cm: ConstructionMethod, n: usize, w: usize) {
     match cm {
         ConstructionMethod::Tree => assert!(
-            n.ilog2() <= usize::BITS,
-            "Within a tree construction, the depth of the tree (N) must be lte to usize::BITS."
+            n.ilog2() as usize <= w * 64,
+            "Within a tree construction, the depth of the tree (N) must be lte to W * 64."
         ),
         ConstructionMethod::Concatenate => assert!(
-            n <= usize::BITS as usize,
-            "Within a concatenative construction, N must be lte to usize::BITS."
+            n <= w * 64,
+            "Within a concatenative construction, N must be lte to W * 64."
         ),
     }
 }
 
+/// The number of `u64` words needed to hold `n` bits.
+pub const fn width_for(n: usize) -> usize {
+    n.div_ceil(64)
+}
+
 /// Specify how to construct the bin identifier given the output of sign(f(q, h)).
 #[derive(PartialEq, Eq, core::marker::ConstParamTy)]
 pub enum ConstructionMethod {
     /// Consider the output of sign(f(q, h)) as the next index to go to within a perfect binary tree.
     Tree,
-    /// Consider the output of sign(f(q, h)) as a single bit; concatenate all bits into a `usize`.
+    /// Consider the output of sign(f(q, h)) as a single bit; concatenate all bits into a `BitCode`.
     Concatenate,
 }
 
-pub struct RandomProjection<'a, T, const D: usize, const NP: usize, const CM: ConstructionMethod> {
-    hp: &'a [[T; D]; NP],
+pub struct RandomProjection<
+    'a,
+    T,
+    const D: usize,
+    const NP: usize,
+    const W: usize,
+    const CM: ConstructionMethod,
+    B = &'a [[T; D]; NP],
+> {
+    hp: B,
+    _marker: core::marker::PhantomData<(&'a (), T)>,
+}
+
+/// A set of `D`-dimensional rows, addressable by index, that hyperplane normals (or the
+/// vectors being indexed) can be stored in. Implemented for a borrowed fixed stack array
+/// so `RandomProjection` stays usable with no allocator at all, and for a borrowed
+/// `Matrix` so large banks can live on the heap instead.
+pub trait HyperplaneBank<'a, T: 'a, const D: usize>: Copy {
+    fn row(self, i: usize) -> &'a [T; D];
+}
+
+impl<'a, T, const D: usize, const NP: usize> HyperplaneBank<'a, T, D> for &'a [[T; D]; NP] {
+    fn row(self, i: usize) -> &'a [T; D] {
+        &self[i]
+    }
 }
 
-impl<'a, T, const D: usize, const NP: usize, const CM: ConstructionMethod> LSH<'a, T, D>
-    for RandomProjection<'a, T, D, NP, CM>
+#[cfg(feature = "alloc")]
+impl<'a, T, const D: usize> HyperplaneBank<'a, T, D> for &'a crate::matrix::Matrix<T> {
+    fn row(self, i: usize) -> &'a [T; D] {
+        (&self[i])
+            .try_into()
+            .expect("matrix row width must equal D")
+    }
+}
+
+impl<'a, T, const D: usize, const NP: usize, const W: usize, const CM: ConstructionMethod, B>
+    LSH<'a, T, D, W> for RandomProjection<'a, T, D, NP, W, CM, B>
 where
-    T: hyperplane::ArcCos<'a, T, D>,
-    ConstAssert<{ fits_in_usize(CM, NP) }>:,
+    T: hyperplane::ArcCos<'a, T, D> + 'a,
+    B: HyperplaneBank<'a, T, D>,
+    ConstAssert<{ fits_in_bitcode(CM, NP, W) }>:,
     // Within a tree construction, we require a `Sign` arr of `log2(N)`; this bound
     // allows for the stack construction of that arr.
     [(); NP.ilog2() as usize]: Sized,
 {
-    fn bin(&self, q: &'a [T; D]) -> usize {
+    fn bin(&self, q: &'a [T; D]) -> BitCode<W> {
         match CM {
             ConstructionMethod::Tree => RandomProjection::tree(q, self.hp),
             ConstructionMethod::Concatenate => RandomProjection::concatenate(q, self.hp),
@@ -52,44 +125,79 @@ where
     }
 }
 
-impl<'a, T, const D: usize, const NP: usize, const CM: ConstructionMethod>
-    RandomProjection<'a, T, D, NP, CM>
+impl<'a, T, const D: usize, const NP: usize, const W: usize, const CM: ConstructionMethod, B>
+    RandomProjection<'a, T, D, NP, W, CM, B>
 where
-    T: hyperplane::ArcCos<'a, T, D>,
-    ConstAssert<{ fits_in_usize(CM, NP) }>:,
+    T: hyperplane::ArcCos<'a, T, D> + 'a,
+    B: HyperplaneBank<'a, T, D>,
+    ConstAssert<{ fits_in_bitcode(CM, NP, W) }>:,
     // Within a tree construction, we require a `Sign` arr of `log2(N)`; this bound
     // allows for the stack construction of that arr.
     [(); NP.ilog2() as usize]: Sized,
 {
-    fn tree(query: &'a [T; D], hp: &'a [[T; D]; NP]) -> usize {
+    fn tree(query: &'a [T; D], hp: B) -> BitCode<W> {
         let mut arr = [hyperplane::Sign::default(); NP.ilog2() as usize];
         let mut idx = 0;
         for mem in arr.iter_mut() {
-            let hp_i = hp.get(idx).unwrap();
+            let hp_i = hp.row(idx);
             *mem = T::sign(query, hp_i);
             // Choose the left/right node for a perfect BT.
             idx = (idx * 2) + Into::<usize>::into(*mem) + 1;
         }
 
-        hyperplane::Sign::to_usize(&arr)
+        hyperplane::Sign::to_bitcode(&arr)
     }
 
-    fn concatenate(query: &'a [T; D], hp: &'a [[T; D]; NP]) -> usize {
+    fn concatenate(query: &'a [T; D], hp: B) -> BitCode<W> {
         let mut arr = [hyperplane::Sign::default(); NP];
-        for (mem, hp_i) in arr.iter_mut().zip(hp.iter()) {
-            *mem = T::sign(query, hp_i);
+        for (i, mem) in arr.iter_mut().enumerate() {
+            *mem = T::sign(query, hp.row(i));
         }
 
-        hyperplane::Sign::to_usize(&arr)
+        hyperplane::Sign::to_bitcode(&arr)
     }
 
-    pub fn new(hp: &'a [[T; D]; NP]) -> Self {
-        Self { hp }
+    pub fn new(hp: B) -> Self {
+        Self {
+            hp,
+            _marker: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<'a, T, const D: usize, const NP: usize, const W: usize, const CM: ConstructionMethod, B>
+    RandomProjection<'a, T, D, NP, W, CM, B>
+where
+    T: hyperplane::ArcCos<'a, T, D>
+        + Default
+        + Copy
+        + PartialOrd
+        + core::ops::Neg<Output = T>
+        + Into<hyperplane::Sign>
+        + 'a,
+    B: HyperplaneBank<'a, T, D>,
+    ConstAssert<{ fits_in_bitcode(CM, NP, W) }>:,
+{
+    /// Like `concatenate`, but alongside the `BitCode` return the distance of `q`'s
+    /// projection onto each hyperplane to the decision boundary (`|<q, h_i>|`). A small
+    /// distance means a near neighbor could plausibly have landed on the other side of
+    /// that hyperplane, which is exactly what multi-probe querying perturbs first.
+    pub fn bin_with_scores(&self, q: &'a [T; D]) -> (BitCode<W>, [T; NP]) {
+        let mut code = BitCode::<W>::default();
+        let mut scores = [T::default(); NP];
+        for (i, score) in scores.iter_mut().enumerate() {
+            let raw = T::project(q, self.hp.row(i));
+            if matches!(raw.into(), hyperplane::Sign::Positive) {
+                code.set(i);
+            }
+            *score = if raw < T::default() { -raw } else { raw };
+        }
+        (code, scores)
     }
 }
 
-mod hyperplane {
-    use super::{fits_in_usize, ConstAssert, ConstructionMethod};
+pub mod hyperplane {
+    use super::BitCode;
 
     #[derive(Copy, Clone, Debug, Default)]
     pub enum Sign {
@@ -99,17 +207,15 @@ mod hyperplane {
     }
 
     impl Sign {
-        // Convert a slice of `Sign` into a single `usize`.
-        pub fn to_usize<const CM: ConstructionMethod, const N: usize>(sign_arr: &[Sign]) -> usize
-        where
-            ConstAssert<{ fits_in_usize(CM, N) }>:,
-        {
-            sign_arr
-                .iter()
-                .enumerate()
-                .fold(0usize, |acc, (idx, value)| {
-                    acc + (Into::<usize>::into(value) << idx)
-                })
+        /// Convert a slice of `Sign` into a `BitCode`, one bit per entry.
+        pub fn to_bitcode<const W: usize>(sign_arr: &[Sign]) -> BitCode<W> {
+            let mut code = BitCode::<W>::default();
+            for (idx, value) in sign_arr.iter().enumerate() {
+                if matches!(value, Sign::Positive) {
+                    code.set(idx);
+                }
+            }
+            code
         }
     }
 
@@ -153,6 +259,9 @@ mod hyperplane {
 
     /// Charikar's SimHash.
     pub trait ArcCos<'c, T, const D: usize> {
+        /// Return the raw inner product of two vectors, before it's reduced to a `Sign`.
+        fn project(a: &'c [T; D], b: &'c [T; D]) -> T;
+
         /// Return the sign of the inner product of two vectors.
         fn sign(a: &'c [T; D], b: &'c [T; D]) -> Sign;
     }
@@ -162,11 +271,14 @@ mod hyperplane {
         T: Default + core::ops::Add<Output = T> + Into<Sign>,
         &'c T: core::ops::Mul<&'c T, Output = T> + 'c,
     {
-        fn sign(a: &'c [T; D], b: &'c [T; D]) -> Sign {
+        fn project(a: &'c [T; D], b: &'c [T; D]) -> T {
             a.iter()
                 .zip(b.iter())
                 .fold(T::default(), |acc, (x, y)| acc + (x * y))
-                .into()
+        }
+
+        fn sign(a: &'c [T; D], b: &'c [T; D]) -> Sign {
+            Self::project(a, b).into()
         }
     }
 
@@ -175,8 +287,7 @@ mod hyperplane {
         use super::*;
 
         #[test]
-        fn test_sign_to_usize() {
-            const CM: ConstructionMethod = ConstructionMethod::Tree;
+        fn test_sign_to_bitcode() {
             const SZ: usize = 5;
             const ARR: [Sign; SZ] = [
                 Sign::Positive,
@@ -186,7 +297,7 @@ mod hyperplane {
                 Sign::Positive,
             ];
 
-            assert_eq!(Sign::to_usize::<CM, SZ>(&ARR), 0b11001);
+            assert_eq!(Sign::to_bitcode::<1>(&ARR).to_usize_truncating(), 0b11001);
         }
     }
 }
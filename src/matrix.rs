@@ -0,0 +1,47 @@
+//! A row-major flat buffer, for placing large vector/hyperplane sets on the heap instead
+//! of as nested stack arrays like `[[T; D]; N]`.
+use alloc::vec::Vec;
+use core::ops::{Index, IndexMut};
+
+/// A flat, heap-backed matrix: a single `Vec<T>` plus a row `stride`. Row `i` is
+/// `&buf[i * stride..][..stride]`, keeping every row contiguous for better cache
+/// locality than a nested stack array.
+pub struct Matrix<T> {
+    buf: Vec<T>,
+    stride: usize,
+}
+
+impl<T> Matrix<T> {
+    /// Wrap `buf` as a matrix with the given row `stride`. `buf.len()` must be a
+    /// multiple of `stride`.
+    pub fn new(buf: Vec<T>, stride: usize) -> Self {
+        assert!(stride > 0, "stride must be non-zero");
+        assert!(
+            buf.len() % stride == 0,
+            "buffer length must be a multiple of stride"
+        );
+        Self { buf, stride }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.buf.len() / self.stride
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+}
+
+impl<T> Index<usize> for Matrix<T> {
+    type Output = [T];
+
+    fn index(&self, i: usize) -> &[T] {
+        &self.buf[i * self.stride..][..self.stride]
+    }
+}
+
+impl<T> IndexMut<usize> for Matrix<T> {
+    fn index_mut(&mut self, i: usize) -> &mut [T] {
+        &mut self.buf[i * self.stride..][..self.stride]
+    }
+}
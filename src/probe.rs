@@ -0,0 +1,118 @@
+//! Multi-probe querying: explore neighboring hash bins in likelihood order instead of
+//! relying on more hash tables to make up for a near neighbor that lands in the wrong
+//! bucket.
+use super::lsh::BitCode;
+
+/// Return `bin` with bit `idx` flipped — a single-bit (Hamming-1) perturbation, the unit
+/// move a multi-probe sequence explores.
+pub(super) fn similar_bin<const W: usize>(bin: BitCode<W>, idx: usize) -> BitCode<W> {
+    bin.flip(idx)
+}
+
+#[cfg(feature = "alloc")]
+mod multi_probe {
+    use super::super::lsh::BitCode;
+    use alloc::collections::BinaryHeap;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    /// A perturbation set: a strictly increasing sequence of indices into the
+    /// hyperplanes sorted by ascending boundary distance. Flipping the bits at these
+    /// sorted positions yields one probe bin.
+    struct PerturbationSet {
+        /// Sum of squared boundary distances of the flipped bits; smaller sorts first.
+        score: f64,
+        indices: Vec<usize>,
+    }
+
+    impl PartialEq for PerturbationSet {
+        fn eq(&self, other: &Self) -> bool {
+            self.score == other.score
+        }
+    }
+    impl Eq for PerturbationSet {}
+
+    impl PartialOrd for PerturbationSet {
+        fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for PerturbationSet {
+        // Reversed so `BinaryHeap` (a max-heap) pops the *lowest*-scored set first.
+        fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+            other.score.partial_cmp(&self.score).unwrap()
+        }
+    }
+
+    /// Generate up to `limit` probe bins derived from `code`, in descending order of the
+    /// estimated likelihood that a true near neighbor landed there instead (ascending
+    /// perturbation score).
+    ///
+    /// `scores[i]` is the distance from the query's projection onto hyperplane `i` to
+    /// the decision boundary (`|<q, h_i>|`); the smaller it is, the more likely that
+    /// bit of `code` is "wrong" relative to a true near neighbor, so it's flipped first.
+    /// Perturbation sets are explored via the standard "shift" (replace the largest
+    /// flipped index with the next one) and "expand" (add the next index) moves, seeded
+    /// from the single-bit flip of the lowest-scored hyperplane.
+    pub(in super::super) fn probe_bins<const W: usize>(
+        code: BitCode<W>,
+        scores: &[f64],
+        limit: usize,
+    ) -> Vec<BitCode<W>> {
+        if scores.is_empty() || limit == 0 {
+            return Vec::new();
+        }
+
+        // Hyperplane indices, sorted by ascending boundary distance.
+        let mut order: Vec<usize> = (0..scores.len()).collect();
+        order.sort_by(|&a, &b| scores[a].partial_cmp(&scores[b]).unwrap());
+
+        let score_of = |indices: &[usize]| -> f64 {
+            indices.iter().map(|&i| scores[order[i]].powi(2)).sum()
+        };
+
+        let mut heap = BinaryHeap::new();
+        heap.push(PerturbationSet {
+            score: score_of(&[0]),
+            indices: vec![0],
+        });
+
+        let mut probes = Vec::with_capacity(limit);
+        while probes.len() < limit {
+            let Some(PerturbationSet { indices, .. }) = heap.pop() else {
+                break;
+            };
+
+            let mut probe = code;
+            for &i in &indices {
+                probe = super::similar_bin(probe, order[i]);
+            }
+            probes.push(probe);
+
+            let last = *indices.last().unwrap();
+            if last + 1 < order.len() {
+                // "shift": replace the largest flipped index with the next one.
+                let mut shifted = indices.clone();
+                *shifted.last_mut().unwrap() = last + 1;
+                heap.push(PerturbationSet {
+                    score: score_of(&shifted),
+                    indices: shifted,
+                });
+
+                // "expand": grow the set with the next index.
+                let mut expanded = indices;
+                expanded.push(last + 1);
+                heap.push(PerturbationSet {
+                    score: score_of(&expanded),
+                    indices: expanded,
+                });
+            }
+        }
+
+        probes
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub(super) use multi_probe::probe_bins;
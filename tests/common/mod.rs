@@ -18,3 +18,26 @@ pub fn build_vectors() -> Vec<(usize, [f32; V_DIM])> {
 
     out
 }
+
+/// Cosine similarity between two vectors of dimension `V_DIM`.
+pub fn cosine_similarity(a: &[f32; V_DIM], b: &[f32; V_DIM]) -> f32 {
+    let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let mag_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let mag_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    dot / (mag_a * mag_b)
+}
+
+/// Brute-force ground truth: the `k` identifiers in `vectors` most cosine-similar to `q`.
+pub fn brute_force_knn(
+    vectors: &[(usize, [f32; V_DIM])],
+    q: &[f32; V_DIM],
+    k: usize,
+) -> Vec<usize> {
+    let mut scored: Vec<(usize, f32)> = vectors
+        .iter()
+        .map(|&(id, v)| (id, cosine_similarity(&v, q)))
+        .collect();
+
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    scored.into_iter().take(k).map(|(id, _)| id).collect()
+}
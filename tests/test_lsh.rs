@@ -1,6 +1,8 @@
 mod common;
 
-use eann_db::lsh::LSHDB;
+use ns_ann::index::LSHDB;
+use ns_ann::lsh::ConstructionMethod;
+use proptest::prelude::*;
 
 // Number of hyperplanes.
 const NB: usize = 4;
@@ -17,5 +19,125 @@ fn test_lshdb() {
     let db = LSHDB::<NB, N, f32, V_DIM, usize>::new(&mut rng, &v_set, None);
 
     let (q_ident, q_vector) = v_set.get(0).unwrap();
-    assert_eq!(db.ann(q_vector).find(|&x| x == q_ident).is_some(), true);
+    assert_eq!(
+        db.ann(q_vector, 1).into_iter().find(|&x| x == q_ident).is_some(),
+        true
+    );
+}
+
+/// Force every vector into the same bucket (identical vectors hash identically no
+/// matter which hyperplanes get drawn) and assert every inserted id is still
+/// reachable with a single-bucket query. A guard against `ArrIndex::build_concatenate`
+/// dropping or misattributing entries at a bin boundary — the proptest recall
+/// invariant below is too forgiving to reliably catch that on its own, since a leak
+/// from a neighboring bin can pad `found` and mask a dropped entry.
+#[test]
+fn test_lshdb_bin_collision() {
+    const COLLIDE_N: usize = 16;
+    let v_set: [(usize, [f32; V_DIM]); COLLIDE_N] =
+        std::array::from_fn(|i| (i, [1.0f32; V_DIM]));
+    let mut rng = rand::thread_rng();
+
+    let db = LSHDB::<NB, COLLIDE_N, f32, V_DIM, usize>::new(&mut rng, &v_set, None);
+
+    for (id, v) in &v_set {
+        assert!(
+            db.ann(v, 1).into_iter().any(|&found| found == *id),
+            "id {id} unreachable via ann after forcing a bin collision"
+        );
+    }
+}
+
+// A smaller `N` than the smoke test above: proptest drives many cases, and each one
+// rebuilds the index from scratch once its batch of inserts fills up.
+const PROP_N: usize = 48;
+const PROP_K: usize = 5;
+const PROP_PROBES: usize = 3;
+const RECALL_THRESHOLD: f64 = 0.5;
+
+/// An operation applied to both the LSH index under test and the brute-force reference
+/// model, mirroring the operation/reference-oracle style used for incremental trees.
+#[derive(Debug, Clone)]
+enum Operation {
+    Insert([f32; V_DIM]),
+    Query { target: usize, k: usize },
+}
+
+fn arb_operation() -> impl Strategy<Value = Operation> {
+    prop_oneof![
+        prop::collection::vec(-1.0f32..1.0f32, V_DIM)
+            .prop_map(|v| Operation::Insert(v.try_into().unwrap())),
+        (0..PROP_N, 1..=PROP_K).prop_map(|(target, k)| Operation::Query { target, k }),
+    ]
+}
+
+/// Drive `ops` through both `LSHDB<_, _, _, _, _, CM>` and a brute-force reference model,
+/// asserting that a self-query always finds the inserted identifier and that average
+/// recall@k stays above `RECALL_THRESHOLD`.
+///
+/// `LSHDB` is built once per batch of `PROP_N` inserts (its backing arrays are sized at
+/// compile time); inserts beyond that are dropped, mirroring a full index.
+fn apply_operations<const CM: ConstructionMethod>(ops: &[Operation]) -> Result<(), TestCaseError>
+where
+    [(); ns_ann::lsh::width_for(NB)]: Sized,
+    [(); 1 << NB]: Sized,
+    [(); NB.ilog2() as usize]: Sized,
+    ns_ann::lsh::ConstAssert<{ ns_ann::lsh::fits_in_bitcode(CM, NB, ns_ann::lsh::width_for(NB)) }>:,
+{
+    let mut pending: Vec<(usize, [f32; V_DIM])> = Vec::new();
+    let mut db: Option<LSHDB<NB, PROP_N, f32, V_DIM, usize, CM>> = None;
+    let mut recalls = Vec::new();
+
+    for op in ops {
+        match op {
+            Operation::Insert(v) => {
+                if db.is_none() && pending.len() < PROP_N {
+                    pending.push((pending.len(), *v));
+                    if pending.len() == PROP_N {
+                        let mut rng = rand::thread_rng();
+                        let batch: [(usize, [f32; V_DIM]); PROP_N] =
+                            pending.clone().try_into().unwrap();
+                        db = Some(LSHDB::new(&mut rng, &batch, None));
+                    }
+                }
+            }
+            Operation::Query { target, k } => {
+                let Some(db) = &db else { continue };
+                let (target_id, q) = pending[*target % pending.len()];
+
+                // Invariant: a self-query always returns the inserted identifier.
+                prop_assert!(db
+                    .ann(&q, PROP_PROBES)
+                    .into_iter()
+                    .any(|&id| id == target_id));
+
+                // Invariant: recall@k stays above a configured threshold.
+                let truth = common::brute_force_knn(&pending, &q, *k);
+                let found: Vec<usize> = db.ann(&q, PROP_PROBES).into_iter().copied().collect();
+                let hits = truth.iter().filter(|id| found.contains(id)).count();
+                recalls.push(hits as f64 / truth.len().max(1) as f64);
+            }
+        }
+    }
+
+    if !recalls.is_empty() {
+        let avg = recalls.iter().sum::<f64>() / recalls.len() as f64;
+        prop_assert!(avg >= RECALL_THRESHOLD, "recall@k {avg} below threshold");
+    }
+
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn recall_and_self_query_concatenate(ops in prop::collection::vec(arb_operation(), 1..400)) {
+        apply_operations::<{ ConstructionMethod::Concatenate }>(&ops)?;
+    }
+
+    #[test]
+    fn recall_and_self_query_tree(ops in prop::collection::vec(arb_operation(), 1..400)) {
+        apply_operations::<{ ConstructionMethod::Tree }>(&ops)?;
+    }
 }